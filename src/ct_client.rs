@@ -0,0 +1,175 @@
+//! Async client for the RFC 6962 §4 CT log HTTP API, plus signed tree head
+//! signature verification (the `tree_head_signature` is the RFC 6962 §2.1.1
+//! `TreeHeadSignature`, a `DigitallySigned` over version/timestamp/
+//! tree_size/root_hash, not an SCT, so `sct::verify_sct` doesn't apply here).
+
+use anyhow::{Context, Result, anyhow, ensure};
+use base64::prelude::*;
+use ring::signature::{self, UnparsedPublicKey};
+use serde::Deserialize;
+
+use crate::CTLog;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct SignedTreeHead {
+    pub(crate) tree_size: u64,
+    pub(crate) timestamp: u64,
+    sha256_root_hash: String,
+    tree_head_signature: String,
+}
+
+impl SignedTreeHead {
+    pub(crate) fn root_hash(&self) -> Result<[u8; 32]> {
+        BASE64_STANDARD
+            .decode(&self.sha256_root_hash)?
+            .try_into()
+            .map_err(|_| anyhow!("sha256_root_hash was not 32 bytes"))
+    }
+
+    /// Verifies the §2.1.1 `TreeHeadSignature` against the log's public key.
+    pub(crate) fn verify_signature(&self, log: &CTLog) -> Result<()> {
+        let mut signed = Vec::with_capacity(1 + 1 + 8 + 8 + 32);
+        signed.push(0); // Version::V1
+        signed.push(1); // SignatureType::tree_hash
+        signed.extend_from_slice(&self.timestamp.to_be_bytes());
+        signed.extend_from_slice(&self.tree_size.to_be_bytes());
+        signed.extend_from_slice(&self.root_hash()?);
+
+        let (verification_algorithm, signature) =
+            decode_digitally_signed(&self.tree_head_signature)?;
+        let key = UnparsedPublicKey::new(verification_algorithm, &log.key);
+        key.verify(&signed, &signature)
+            .map_err(|_| anyhow!("STH signature verification failed for {}", log.description))
+    }
+}
+
+/// RFC 6962 §3.2 `HashAlgorithm`.
+const HASH_SHA256: u8 = 4;
+
+/// RFC 6962 §3.2 `SignatureAlgorithm`.
+const SIG_RSA: u8 = 1;
+const SIG_ECDSA: u8 = 3;
+
+/// Maps a `DigitallySigned` header's (hash algorithm, signature algorithm)
+/// byte pair to the `ring` verification algorithm it identifies.
+pub(crate) fn verification_algorithm_for(
+    hash_algo: u8,
+    sig_algo: u8,
+) -> Result<&'static dyn signature::VerificationAlgorithm> {
+    match (hash_algo, sig_algo) {
+        (HASH_SHA256, SIG_ECDSA) => Ok(&signature::ECDSA_P256_SHA256_ASN1),
+        (HASH_SHA256, SIG_RSA) => Ok(&signature::RSA_PKCS1_2048_8192_SHA256),
+        _ => Err(anyhow!(
+            "unsupported DigitallySigned algorithm pair (hash={hash_algo}, sig={sig_algo})"
+        )),
+    }
+}
+
+/// Parses the RFC 6962 §3.2 `DigitallySigned` header (hash algorithm,
+/// signature algorithm, 2-byte length) and returns the verification
+/// algorithm it identifies together with the raw signature bytes.
+fn decode_digitally_signed(
+    b64: &str,
+) -> Result<(&'static dyn signature::VerificationAlgorithm, Vec<u8>)> {
+    let raw = BASE64_STANDARD.decode(b64)?;
+    ensure!(raw.len() > 4, "tree_head_signature is too short");
+    let verification_algorithm = verification_algorithm_for(raw[0], raw[1])?;
+    let sig_len = u16::from_be_bytes(raw[2..4].try_into()?) as usize;
+    ensure!(
+        raw.len() == 4 + sig_len,
+        "tree_head_signature length does not match its signature field"
+    );
+    Ok((verification_algorithm, raw[4..].to_vec()))
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct InclusionProof {
+    pub(crate) leaf_index: u64,
+    audit_path: Vec<String>,
+}
+
+impl InclusionProof {
+    pub(crate) fn audit_path(&self) -> Result<Vec<[u8; 32]>> {
+        self.audit_path
+            .iter()
+            .map(|node| {
+                BASE64_STANDARD
+                    .decode(node)?
+                    .try_into()
+                    .map_err(|_| anyhow!("audit path node was not 32 bytes"))
+            })
+            .collect()
+    }
+}
+
+/// A minimal async client for one CT log's RFC 6962 §4 HTTP endpoints.
+pub(crate) struct CtClient {
+    http: reqwest::Client,
+    base_url: String,
+}
+
+impl CtClient {
+    pub(crate) fn new(log: &CTLog) -> Self {
+        CtClient {
+            http: reqwest::Client::new(),
+            base_url: log.url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// `GET https://<log server>/ct/v1/get-sth`
+    pub(crate) async fn get_sth(&self) -> Result<SignedTreeHead> {
+        self.http
+            .get(format!("{}/ct/v1/get-sth", self.base_url))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+            .context("failed to parse get-sth response")
+    }
+
+    /// `GET https://<log server>/ct/v1/get-proof-by-hash`
+    pub(crate) async fn get_proof_by_hash(
+        &self,
+        leaf_hash: [u8; 32],
+        tree_size: u64,
+    ) -> Result<InclusionProof> {
+        self.http
+            .get(format!("{}/ct/v1/get-proof-by-hash", self.base_url))
+            .query(&[
+                ("hash", BASE64_STANDARD.encode(leaf_hash)),
+                ("tree_size", tree_size.to_string()),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+            .context("failed to parse get-proof-by-hash response")
+    }
+
+    /// `POST https://<log server>/ct/v1/add-chain`
+    ///
+    /// Not called anywhere yet - this tool only verifies SCTs embedded in
+    /// certificates it's handed, it doesn't submit chains to get new ones -
+    /// but it's part of the §4 API surface `CtClient` wraps, kept here for
+    /// the submission workflow this binary doesn't implement yet.
+    #[allow(dead_code)]
+    pub(crate) async fn add_chain(&self, chain: &[Vec<u8>]) -> Result<serde_json::Value> {
+        let body = serde_json::json!({
+            "chain": chain
+                .iter()
+                .map(|der| BASE64_STANDARD.encode(der))
+                .collect::<Vec<_>>(),
+        });
+        self.http
+            .post(format!("{}/ct/v1/add-chain", self.base_url))
+            .json(&body)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+            .context("failed to parse add-chain response")
+    }
+}
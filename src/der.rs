@@ -0,0 +1,122 @@
+//! Minimal DER TLV (tag-length-value) primitives, just enough to splice an
+//! extension out of a `TBSCertificate` without re-encoding the whole
+//! certificate through a full ASN.1 writer.
+
+use anyhow::{Result, ensure};
+
+/// Reads one DER TLV off the front of `buf` and returns its tag, its
+/// content slice, and the number of bytes it occupied (header + content).
+pub(crate) fn read_tlv(buf: &[u8]) -> Result<(u8, &[u8], usize)> {
+    ensure!(buf.len() >= 2, "DER buffer too short for a TLV header");
+    let tag = buf[0];
+    let (len, len_bytes) = read_length(&buf[1..])?;
+    let header_len = 1 + len_bytes;
+    ensure!(
+        buf.len() >= header_len + len,
+        "DER TLV length exceeds the remaining buffer"
+    );
+    Ok((tag, &buf[header_len..header_len + len], header_len + len))
+}
+
+fn read_length(buf: &[u8]) -> Result<(usize, usize)> {
+    ensure!(!buf.is_empty(), "DER buffer too short for a length");
+    let first = buf[0];
+    if first & 0x80 == 0 {
+        return Ok((first as usize, 1));
+    }
+    let n = (first & 0x7f) as usize;
+    ensure!(
+        n > 0 && n <= 8,
+        "unsupported DER long-form length encoding"
+    );
+    ensure!(buf.len() > n, "DER buffer too short for a long-form length");
+    let mut len = 0usize;
+    for &b in &buf[1..1 + n] {
+        len = (len << 8) | b as usize;
+    }
+    Ok((len, 1 + n))
+}
+
+/// Skips `n` complete TLVs from the front of `buf` and returns what's left.
+pub(crate) fn skip_tlvs(mut buf: &[u8], n: usize) -> Result<&[u8]> {
+    for _ in 0..n {
+        let (_, _, consumed) = read_tlv(buf)?;
+        buf = &buf[consumed..];
+    }
+    Ok(buf)
+}
+
+/// Encodes `content` as a DER TLV under `tag`.
+pub(crate) fn encode_tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(1 + 5 + content.len());
+    out.push(tag);
+    out.extend(encode_length(content.len()));
+    out.extend_from_slice(content);
+    out
+}
+
+fn encode_length(len: usize) -> Vec<u8> {
+    if len < 0x80 {
+        return vec![len as u8];
+    }
+    let bytes = len.to_be_bytes();
+    let first_nonzero = bytes.iter().position(|&b| b != 0).unwrap_or(bytes.len() - 1);
+    let significant = &bytes[first_nonzero..];
+    let mut out = vec![0x80 | significant.len() as u8];
+    out.extend_from_slice(significant);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_short_form_length() {
+        let encoded = encode_tlv(0x30, b"hello");
+        let (tag, content, consumed) = read_tlv(&encoded).unwrap();
+        assert_eq!(tag, 0x30);
+        assert_eq!(content, b"hello");
+        assert_eq!(consumed, encoded.len());
+    }
+
+    #[test]
+    fn round_trips_long_form_length() {
+        let content = vec![0x42; 300];
+        let encoded = encode_tlv(0x04, &content);
+        assert_eq!(&encoded[1..4], &[0x82, 0x01, 0x2c]); // 300 as a 2-byte long-form length
+
+        let (tag, decoded, consumed) = read_tlv(&encoded).unwrap();
+        assert_eq!(tag, 0x04);
+        assert_eq!(decoded, content.as_slice());
+        assert_eq!(consumed, encoded.len());
+    }
+
+    #[test]
+    fn read_tlv_ignores_trailing_bytes() {
+        let mut buf = encode_tlv(0x30, b"first");
+        buf.extend_from_slice(&encode_tlv(0x31, b"second"));
+
+        let (_, _, consumed) = read_tlv(&buf).unwrap();
+        let (tag, content, _) = read_tlv(&buf[consumed..]).unwrap();
+        assert_eq!(tag, 0x31);
+        assert_eq!(content, b"second");
+    }
+
+    #[test]
+    fn skip_tlvs_skips_the_requested_count() {
+        let mut buf = encode_tlv(0x30, b"a");
+        buf.extend_from_slice(&encode_tlv(0x30, b"b"));
+        buf.extend_from_slice(&encode_tlv(0x30, b"c"));
+
+        let rest = skip_tlvs(&buf, 2).unwrap();
+        let (_, content, _) = read_tlv(rest).unwrap();
+        assert_eq!(content, b"c");
+    }
+
+    #[test]
+    fn read_tlv_rejects_a_truncated_buffer() {
+        let encoded = encode_tlv(0x30, b"hello");
+        assert!(read_tlv(&encoded[..encoded.len() - 1]).is_err());
+    }
+}
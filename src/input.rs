@@ -0,0 +1,41 @@
+//! Loads the certificate (and, ideally, its issuer chain) to check: from a
+//! PEM or DER file named on the command line, or from stdin if no path was
+//! given. PEM input may hold more than one certificate; in that case the
+//! leaf is expected first, followed by its issuer(s).
+
+use std::io::Read;
+use std::{fs, io};
+
+use anyhow::{Context, Result, ensure};
+use x509_parser::pem::Pem;
+
+/// Loads a DER-encoded certificate chain, leaf first.
+pub(crate) fn load_chain(path: Option<&str>) -> Result<Vec<Vec<u8>>> {
+    let data = match path {
+        Some(path) => fs::read(path).with_context(|| format!("failed to read {path}"))?,
+        None => {
+            let mut buf = Vec::new();
+            io::stdin()
+                .read_to_end(&mut buf)
+                .context("failed to read certificate chain from stdin")?;
+            buf
+        }
+    };
+
+    if looks_like_pem(&data) {
+        let mut chain = Vec::new();
+        for pem in Pem::iter_from_buffer(&data) {
+            let pem = pem.context("failed to parse a PEM block")?;
+            chain.push(pem.contents);
+        }
+        ensure!(!chain.is_empty(), "no certificates found in PEM input");
+        Ok(chain)
+    } else {
+        Ok(vec![data])
+    }
+}
+
+fn looks_like_pem(data: &[u8]) -> bool {
+    data.windows(b"-----BEGIN".len())
+        .any(|window| window == b"-----BEGIN")
+}
@@ -0,0 +1,264 @@
+//! TUF-style trust store for the set of known CT logs.
+//!
+//! The log list is fetched over the network together with a detached
+//! signature and authenticated against [`PUBLISHER_KEY`], a publisher key
+//! pinned at build time, before it is ever parsed. A candidate list is
+//! accepted only if its `version` is not a rollback of the last accepted
+//! version and it has not passed its `expiry`. The last accepted list and
+//! its detached signature are both cached on disk so a restart doesn't have
+//! to fall back to the embedded copy - the signature is re-verified on every
+//! load, so a disk cache living at a predictable path can't be used to slip
+//! in an unsigned trust store. The embedded copy (`version` 0) is used only
+//! until a verified list has been loaded from the network or the disk cache.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::PathBuf,
+    sync::{Mutex, OnceLock},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+use base64::prelude::*;
+use ring::signature::{self, UnparsedPublicKey};
+
+use crate::CTLog;
+
+/// The log-list publisher's Ed25519 public key, pinned at build time.
+const PUBLISHER_KEY: &[u8] = include_bytes!("ct_log_list_publisher.pub");
+
+const LIST_URL: &str = "https://www.gstatic.com/ct/log_list/v3/log_list.json";
+const SIGNATURE_URL: &str = "https://www.gstatic.com/ct/log_list/v3/log_list.sig";
+
+/// A per-user cache directory, preferred over the shared system temp
+/// directory - a fixed, world-writable path would let any other local user
+/// plant a validly-signed-but-stale log list as the initial trusted state.
+fn cache_dir() -> PathBuf {
+    let base = std::env::var_os("HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(std::env::temp_dir);
+    base.join(".cache").join("testct")
+}
+
+fn cache_path() -> PathBuf {
+    cache_dir().join("log-list.json")
+}
+
+fn signature_cache_path() -> PathBuf {
+    cache_dir().join("log-list.sig")
+}
+
+/// Writes `contents` to `path`, creating its parent directory if needed, and
+/// restricts both to the owner only so another local user sharing the same
+/// temp-dir fallback can't read or replace the cached trust store.
+fn write_cache_file(path: &std::path::Path, contents: &[u8]) -> Result<()> {
+    let dir = path.parent().expect("cache path always has a parent");
+    fs::create_dir_all(dir)?;
+    restrict_to_owner(dir)?;
+    fs::write(path, contents)?;
+    restrict_to_owner(path)
+}
+
+#[cfg(unix)]
+fn restrict_to_owner(path: &std::path::Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o700))?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn restrict_to_owner(_path: &std::path::Path) -> Result<()> {
+    Ok(())
+}
+
+struct LogListState {
+    version: u64,
+    expiry: u64,
+    logs: HashMap<[u8; 32], CTLog>,
+}
+
+static STATE: OnceLock<Mutex<LogListState>> = OnceLock::new();
+
+fn state() -> &'static Mutex<LogListState> {
+    STATE.get_or_init(|| {
+        let initial = load_disk_cache()
+            .or_else(|_| load_embedded())
+            .expect("embedded CT log list is corrupt");
+        Mutex::new(initial)
+    })
+}
+
+/// The version (serial number) of the currently trusted log list. `0`
+/// means only the embedded fallback copy has ever been loaded.
+pub(crate) fn log_list_version() -> u64 {
+    state().lock().unwrap().version
+}
+
+/// A clone of the currently trusted logs, keyed by log ID.
+pub(crate) fn current_log_list() -> HashMap<[u8; 32], CTLog> {
+    state().lock().unwrap().logs.clone()
+}
+
+/// Fetches a fresh log list and detached signature, verifies the signature
+/// against the pinned publisher key, and - if it is not an expired or
+/// rolled-back version - makes it the active list and caches it on disk.
+pub(crate) async fn refresh_log_list() -> Result<()> {
+    let http = reqwest::Client::new();
+    let body = http
+        .get(LIST_URL)
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+    let raw_signature = http
+        .get(SIGNATURE_URL)
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+
+    accept_candidate(&body, &raw_signature)?;
+    write_cache_file(&cache_path(), &body).context("failed to cache log list to disk")?;
+    write_cache_file(&signature_cache_path(), &raw_signature)
+        .context("failed to cache log list signature to disk")?;
+    Ok(())
+}
+
+/// Loads the disk-cached list and its cached detached signature, re-running
+/// the same signature and expiry checks `accept_candidate` applies to a
+/// freshly fetched list - a cache file sitting at a predictable path must
+/// not be trusted any more than an arbitrary download would be.
+fn load_disk_cache() -> Result<LogListState> {
+    let body = fs::read(cache_path())?;
+    let raw_signature = fs::read(signature_cache_path())?;
+    verify_and_parse(&body, &raw_signature)
+}
+
+fn load_embedded() -> Result<LogListState> {
+    let embedded = include_str!("google_ct_log_list.json");
+    parse_log_list(&serde_json::from_str(embedded)?)
+}
+
+/// Verifies `body` against `raw_signature` under [`PUBLISHER_KEY`] and parses
+/// it, rejecting a list that has already passed its `expiry`.
+fn verify_and_parse(body: &[u8], raw_signature: &[u8]) -> Result<LogListState> {
+    let key = UnparsedPublicKey::new(&signature::ED25519, PUBLISHER_KEY);
+    key.verify(body, raw_signature)
+        .map_err(|_| anyhow!("log list signature verification failed"))?;
+
+    let candidate = parse_log_list(&serde_json::from_slice(body)?)?;
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    if candidate.expiry <= now {
+        bail!(
+            "fetched log list expired at {} (now {now})",
+            candidate.expiry
+        );
+    }
+
+    Ok(candidate)
+}
+
+/// Verifies `body` against `raw_signature` and, if its version is not older
+/// than the last accepted version and it is not expired, installs it as the
+/// active list.
+fn accept_candidate(body: &[u8], raw_signature: &[u8]) -> Result<()> {
+    let candidate = verify_and_parse(body, raw_signature)?;
+
+    let mut guard = state().lock().unwrap();
+    if candidate.version < guard.version {
+        bail!(
+            "refusing to roll back log list from version {} to version {}",
+            guard.version,
+            candidate.version
+        );
+    }
+
+    *guard = candidate;
+    Ok(())
+}
+
+fn parse_log_list(list: &serde_json::Value) -> Result<LogListState> {
+    let version = list
+        .get("version")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(0);
+    let expiry = list
+        .get("expiry")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(u64::MAX);
+
+    let mut logs = HashMap::new();
+
+    let operators = list
+        .get("operators")
+        .ok_or(anyhow!("Failed to get operators from ct logs"))?
+        .as_array()
+        .ok_or(anyhow!("Failed to get array of operators from ct logs"))?
+        .clone();
+
+    for operator in operators {
+        let entries = operator
+            .get("logs")
+            .ok_or(anyhow!("Failed to get logs from ct logs"))?
+            .as_array()
+            .ok_or(anyhow!("Failed to get array of logs from ct logs"))?
+            .clone();
+        for log in entries {
+            let id: &[u8] = &BASE64_STANDARD.decode(
+                log.get("log_id")
+                    .ok_or(anyhow!("Failed to get log_id from ct logs"))?
+                    .as_str()
+                    .ok_or(anyhow!("Failed to get log_id str from ct logs"))?,
+            )?;
+
+            let curr_log = CTLog {
+                description: log
+                    .get("description")
+                    .ok_or(anyhow!("Failed to get description from ct logs"))?
+                    .to_string(),
+                url: log
+                    .get("url")
+                    .ok_or(anyhow!("Failed to get url from ct logs"))?
+                    .as_str()
+                    .ok_or(anyhow!("Failed to get url str from ct logs"))?
+                    .to_string(),
+                operated_by: operator
+                    .get("name")
+                    .ok_or(anyhow!("Failed to get name from ct logs"))?
+                    .to_string(),
+                key: BASE64_STANDARD.decode(
+                    log.get("key")
+                        .ok_or(anyhow!("Failed to get key from ct logs"))?
+                        .as_str()
+                        .ok_or(anyhow!("Failed to get key str from ct logs"))?,
+                )?,
+                id: id.try_into()?,
+                max_merge_delay: log
+                    .get("mmd")
+                    .ok_or(anyhow!("Failed to get mmd from ct logs"))?
+                    .as_u64()
+                    .ok_or(anyhow!("Failed to get mmd str from ct logs"))?
+                    .try_into()?,
+                // The log-list schema reports log status as a `state`
+                // object with a single key naming the current state
+                // ("usable", "qualified", "retired", "rejected", ...).
+                retired: log
+                    .get("state")
+                    .and_then(|s| s.as_object())
+                    .is_some_and(|s| s.contains_key("retired") || s.contains_key("rejected")),
+            };
+
+            logs.insert(id.try_into()?, curr_log);
+        }
+    }
+
+    Ok(LogListState {
+        version,
+        expiry,
+        logs,
+    })
+}
@@ -1,97 +1,66 @@
+mod ct_client;
+mod der;
+mod input;
+mod log_list;
+mod merkle;
+mod policy;
+mod precert;
+
 use anyhow::Result;
-use base64::prelude::*;
+use ct_client::CtClient;
+use merkle::LogEntryType;
+use policy::{SctOutcome, SctRejection};
 use sct::{Log as SctLog, verify_sct};
-use std::{collections::HashMap, time::SystemTime};
+use std::time::SystemTime;
 use x509_parser::{
     certificate::X509Certificate,
     prelude::{FromDer, ParsedExtension},
 };
 
-struct CTLog {
-    description: String,
-    url: String,
-    operated_by: String,
-    key: Vec<u8>,
-    id: [u8; 32],
-    max_merge_delay: usize,
+#[derive(Clone)]
+pub(crate) struct CTLog {
+    pub(crate) description: String,
+    pub(crate) url: String,
+    pub(crate) operated_by: String,
+    pub(crate) key: Vec<u8>,
+    pub(crate) id: [u8; 32],
+    pub(crate) max_merge_delay: usize,
+    pub(crate) retired: bool,
 }
 
-fn parse_ct_log_list() -> Result<HashMap<[u8; 32], CTLog>> {
-    let mut ct_logs_map = HashMap::new();
-
-    let google_ct_logs = include_str!("google_ct_log_list.json");
-    let google_ct_logs = serde_json::from_str::<serde_json::Value>(google_ct_logs)?;
-
-    let operators = google_ct_logs
-        .get("operators")
-        .ok_or(anyhow::anyhow!("Failed to get operators from ct logs"))?
-        .as_array()
-        .ok_or(anyhow::anyhow!(
-            "Failed to get array of operators from ct logs"
-        ))?
-        .clone();
-
-    for operator in operators {
-        let logs = operator
-            .get("logs")
-            .ok_or(anyhow::anyhow!("Failed to get logs from ct logs"))?
-            .as_array()
-            .ok_or(anyhow::anyhow!("Failed to get array of logs from ct logs"))?
-            .clone();
-        for log in logs.clone() {
-            let id: &[u8] = &BASE64_STANDARD.decode(
-                log.get("log_id")
-                    .ok_or(anyhow::anyhow!("Failed to get log_id from ct logs"))?
-                    .as_str()
-                    .ok_or(anyhow::anyhow!("Failed to get log_id str from ct logs"))?,
-            )?;
-
-            let curr_log = CTLog {
-                description: log
-                    .get("description")
-                    .ok_or(anyhow::anyhow!("Failed to get description from ct logs"))?
-                    .to_string(),
-                url: log
-                    .get("url")
-                    .ok_or(anyhow::anyhow!("Failed to get url from ct logs"))?
-                    .to_string(),
-                operated_by: operator
-                    .get("name")
-                    .ok_or(anyhow::anyhow!("Failed to get name from ct logs"))?
-                    .to_string(),
-                key: BASE64_STANDARD.decode(
-                    log.get("key")
-                        .ok_or(anyhow::anyhow!("Failed to get key from ct logs"))?
-                        .as_str()
-                        .ok_or(anyhow::anyhow!("Failed to get key str from ct logs"))?,
-                )?,
-                id: id.try_into()?,
-                max_merge_delay: log
-                    .get("mmd")
-                    .ok_or(anyhow::anyhow!("Failed to get mmd from ct logs"))?
-                    .as_u64()
-                    .ok_or(anyhow::anyhow!("Failed to get mmd str from ct logs"))?
-                    .try_into()?,
-            };
-
-            ct_logs_map.insert(id.try_into()?, curr_log);
-        }
+#[tokio::main]
+async fn main() -> Result<()> {
+    // A path to a PEM/DER leaf certificate (optionally followed by its
+    // issuer chain) given as the first argument, or stdin if omitted.
+    let chain = input::load_chain(std::env::args().nth(1).as_deref())?;
+    let raw_cert = chain[0].clone();
+    let issuer = chain.get(1);
+    if issuer.is_none() {
+        println!(
+            "warning: no issuer certificate provided; embedded SCTs will be verified as if \
+             logged against the final certificate rather than the precertificate"
+        );
     }
 
-    Ok(ct_logs_map)
-}
-
-fn main() -> Result<()> {
-    // duckduckgo.com certificate in PEM format
-    let key = "MIIG7DCCBdSgAwIBAgIQBfWCDIF/sLMaASNII3oOdTANBgkqhkiG9w0BAQsFADBZMQswCQYDVQQGEwJVUzEVMBMGA1UEChMMRGlnaUNlcnQgSW5jMTMwMQYDVQQDEypEaWdpQ2VydCBHbG9iYWwgRzIgVExTIFJTQSBTSEEyNTYgMjAyMCBDQTEwHhcNMjUwMzE5MDAwMDAwWhcNMjUxMjE5MjM1OTU5WjBsMQswCQYDVQQGEwJVUzEVMBMGA1UECBMMUGVubnN5bHZhbmlhMQ4wDAYDVQQHEwVQYW9saTEbMBkGA1UEChMSRHVjayBEdWNrIEdvLCBJbmMuMRkwFwYDVQQDDBAqLmR1Y2tkdWNrZ28uY29tMIIBIjANBgkqhkiG9w0BAQEFAAOCAQ8AMIIBCgKCAQEAjmj8uThud3LkNdHWceX04KWrXbxhRxHeXBqe3ZLYSRAYCw9yfwNFFCHVohVt8KyEm7G3pfC4agTI3bCh1gG/cUtEUjNuKaQireb5HvaSpgVf0X8YSnZgFT3ktpKuRmfkeVSRu1dNbygRCL/YTBP13I3RGnAXtua6u7/IoPQTIMQI/9JbYazrRSVxP5kXN/paMrMe/ZIicvN9jSUtjkuR7wnsLh76OThgAq8velhr6HJINHHiwIUc3CWSicRw+xx1PoPpuh23rDp1mDXAr27+0ATWZEPgg1/p0dpki8+Re16nD1MSPBIIe2EKb0UjKhWFXR4EYEQguKed9J7rrscn1wIDAQABo4IDmzCCA5cwHwYDVR0jBBgwFoAUdIWAwGbH3zfez70pN6oDHb7tzRcwHQYDVR0OBBYEFO88x14PChWwT/0nf5muZfM9OeM5MCsGA1UdEQQkMCKCECouZHVja2R1Y2tnby5jb22CDmR1Y2tkdWNrZ28uY29tMD4GA1UdIAQ3MDUwMwYGZ4EMAQICMCkwJwYIKwYBBQUHAgEWG2h0dHA6Ly93d3cuZGlnaWNlcnQuY29tL0NQUzAOBgNVHQ8BAf8EBAMCBaAwHQYDVR0lBBYwFAYIKwYBBQUHAwEGCCsGAQUFBwMCMIGfBgNVHR8EgZcwgZQwSKBGoESGQmh0dHA6Ly9jcmwzLmRpZ2ljZXJ0LmNvbS9EaWdpQ2VydEdsb2JhbEcyVExTUlNBU0hBMjU2MjAyMENBMS0xLmNybDBIoEagRIZCaHR0cDovL2NybDQuZGlnaWNlcnQuY29tL0RpZ2lDZXJ0R2xvYmFsRzJUTFNSU0FTSEEyNTYyMDIwQ0ExLTEuY3JsMIGHBggrBgEFBQcBAQR7MHkwJAYIKwYBBQUHMAGGGGh0dHA6Ly9vY3NwLmRpZ2ljZXJ0LmNvbTBRBggrBgEFBQcwAoZFaHR0cDovL2NhY2VydHMuZGlnaWNlcnQuY29tL0RpZ2lDZXJ0R2xvYmFsRzJUTFNSU0FTSEEyNTYyMDIwQ0ExLTEuY3J0MAwGA1UdEwEB/wQCMAAwggF9BgorBgEEAdZ5AgQCBIIBbQSCAWkBZwB1ABLxTjS9U3JMhAYZw48/ehP457Vih4icbTAFhOvlhiY6AAABla3RSK4AAAQDAEYwRAIgFfAWv7Jcn71nFNaUfAplrIFjzEDZrp62mcXdUWoo4L0CIDn3hxgqcXcZrX570NyQgpZDc3PKIRNXwJCiq+hcLbQmAHYA7TxL1ugGwqSiAFfbyyTiOAHfUS/txIbFcA8g3bc+P+AAAAGVrdFI6AAABAMARzBFAiEAv5TrBVTzgr9x4Tejii77wtnMooy5rhEJwx4WeWdIwvoCIBJjIFdkm/t4F0W363JUxTXJz7ndKYzvE6fAeo/sq7RuAHYA5tIxY0B3jMEQQQbXcbnOwdJA9paEhvu6hzId/R43jlAAAAGVrdFI7QAABAMARzBFAiAVmyIxIBAwboPJxthfGxawWX/OEQbcON8V5LZmS8sQZwIhAO7lmW/Qc4ccJJO3wz/kjLpVr/HEdOeN73dLAC4RkBrhMA0GCSqGSIb3DQEBCwUAA4IBAQB55g9dBVoIAsCMoNAK/LepvE4uwzSNMSC31JUR2mvgrrw4Y6Y9hl1rs7ITCcmojF0AFlzwdUnpy66lcfEJ/v5ZQeclXtnIhASkSj4hnmax93gvxjz15dSe7IXowKPDP6Jh2nJDF4+y0Q3R0pEse8YHeyuxlLulSQPdfO558NcLrcvKFhRmmHjX0tAhVX17n+GUoBQG1f0Oe36POmVjhEa+Z7RIMX2YeXgRdtx/emvNGYNIq+Ex+0SLQt5ArMA7vthcJ6wpLEWj0Ye+ZYH2bMI7aqbxleoBODHS7TrXhnfTg5mG3M9w0WOzSEJlVltlqs+fBLMPOhZZ0PheGXtXOwzL";
-    let raw_cert = BASE64_STANDARD.decode(key)?;
-
     let Ok((_rem, x509cert)) = X509Certificate::from_der(&raw_cert) else {
         anyhow::bail!("Server Certificate Verification Failed: x509 - Bad Encoding.");
     };
 
+    if let Err(error) = log_list::refresh_log_list().await {
+        println!(
+            "warning: could not refresh CT log list (staying on version {}): {:?}",
+            log_list::log_list_version(),
+            error
+        );
+    }
+
     // Due to weird formatting for Log in SCT create a seperate list first
-    let log_list = parse_ct_log_list()?;
+    let log_list = log_list::current_log_list();
+
+    let validity = x509cert.validity();
+    let validity_days = (validity.not_after.timestamp() - validity.not_before.timestamp()) / 86400;
+
+    let mut sct_outcomes: Vec<SctOutcome> = Vec::new();
 
     for extension in x509cert.extensions() {
         if extension.oid.to_id_string() == "1.3.6.1.4.1.11129.2.4.2".to_string() {
@@ -101,7 +70,25 @@ fn main() -> Result<()> {
                 for sct in scts {
                     // This is unnecissary, could just pass the whole of the Log in rather than the one
                     // that matches, but did this to eliminate one possible error
-                    let tmp_ct_log = log_list.get(sct.id.key_id).unwrap();
+                    let tmp_ct_log = match log_list.get(sct.id.key_id) {
+                        Some(log) if log.retired => {
+                            sct_outcomes.push(SctOutcome {
+                                log_description: log.description.clone(),
+                                operated_by: log.operated_by.clone(),
+                                accepted: Err(SctRejection::RetiredLog),
+                            });
+                            continue;
+                        }
+                        Some(log) => log,
+                        None => {
+                            sct_outcomes.push(SctOutcome {
+                                log_description: format!("{:x?}", sct.id.key_id),
+                                operated_by: "unknown".to_string(),
+                                accepted: Err(SctRejection::UnknownLog),
+                            });
+                            continue;
+                        }
+                    };
                     let tmp_log = SctLog {
                         description: &tmp_ct_log.description,
                         url: &tmp_ct_log.url,
@@ -124,9 +111,78 @@ fn main() -> Result<()> {
                         .unwrap()
                         .as_secs();
 
-                    if let Err(error) = verify_sct(&raw_cert, sct_raw, now, &[&tmp_log]) {
-                        println!("{:?} :: {}", error, "Failed to verify sct");
+                    // An SCT embedded in a certificate's own extension was
+                    // necessarily logged against the precertificate: the
+                    // final certificate didn't exist yet when it was
+                    // signed. Without an issuer we can't rebuild that
+                    // precertificate, so fall back to treating it as an
+                    // X509Entry (and accept that verification may fail).
+                    let (entry_type, signed_entry, verified) = match issuer {
+                        Some(issuer) => {
+                            // The `sct` crate can't verify `PrecertEntry` SCTs
+                            // (it always signs/verifies as `X509Entry`), so
+                            // this path is verified directly against the log.
+                            let precert_entry =
+                                match precert::build_precert_entry(&raw_cert, issuer) {
+                                    Ok(precert_entry) => precert_entry,
+                                    Err(error) => {
+                                        println!(
+                                            "{error:?} :: failed to reconstruct PrecertEntry"
+                                        );
+                                        sct_outcomes.push(SctOutcome {
+                                            log_description: tmp_ct_log.description.clone(),
+                                            operated_by: tmp_ct_log.operated_by.clone(),
+                                            accepted: Err(SctRejection::MalformedPrecert),
+                                        });
+                                        continue;
+                                    }
+                                };
+                            let verified =
+                                precert::verify_precert_sct(tmp_ct_log, &sct, &precert_entry, now);
+                            (LogEntryType::PrecertEntry, precert_entry, verified)
+                        }
+                        None => {
+                            // RFC 6962 §3.1 `ASN.1Cert`: a 3-byte length-prefixed DER certificate.
+                            let mut asn1_cert = Vec::with_capacity(3 + raw_cert.len());
+                            asn1_cert.extend_from_slice(&(raw_cert.len() as u32).to_be_bytes()[1..]);
+                            asn1_cert.extend_from_slice(&raw_cert);
+                            let verified = verify_sct(&raw_cert, sct_raw, now, &[&tmp_log])
+                                .map(|_| ())
+                                .map_err(|error| anyhow::anyhow!("{error:?}"));
+                            (LogEntryType::X509Entry, asn1_cert, verified)
+                        }
+                    };
+
+                    if let Err(error) = verified {
+                        println!("{error:?} :: Failed to verify sct");
+                        sct_outcomes.push(SctOutcome {
+                            log_description: tmp_ct_log.description.clone(),
+                            operated_by: tmp_ct_log.operated_by.clone(),
+                            accepted: Err(SctRejection::BadSignature),
+                        });
+                        continue;
                     }
+
+                    let leaf_hash = merkle::leaf_hash(
+                        sct.timestamp,
+                        entry_type,
+                        &signed_entry,
+                        sct.extensions.0,
+                    );
+
+                    // A valid SCT signature only proves the log *promised*
+                    // to include the entry; it's only CT-compliant once
+                    // that promise is shown to have been kept against a
+                    // verified STH.
+                    let client = CtClient::new(tmp_ct_log);
+                    let accepted =
+                        verify_log_inclusion(&client, tmp_ct_log, leaf_hash, sct.timestamp, now)
+                            .await;
+                    sct_outcomes.push(SctOutcome {
+                        log_description: tmp_ct_log.description.clone(),
+                        operated_by: tmp_ct_log.operated_by.clone(),
+                        accepted,
+                    });
                 }
             } else {
                 anyhow::bail!("Failed to parse SCT extension");
@@ -134,5 +190,80 @@ fn main() -> Result<()> {
         }
     }
 
+    let policy_result = policy::evaluate(sct_outcomes, validity_days);
+    if policy_result.compliant {
+        println!(
+            "CT-compliant: {} valid SCT(s) from {} distinct operator(s) (required {} from >= {})",
+            policy_result.counted.len(),
+            policy_result.distinct_operators,
+            policy_result.required_scts,
+            policy_result.required_operators
+        );
+    } else {
+        println!(
+            "not CT-compliant: {} valid SCT(s) from {} distinct operator(s), required {} from >= {}",
+            policy_result.counted.len(),
+            policy_result.distinct_operators,
+            policy_result.required_scts,
+            policy_result.required_operators
+        );
+        for rejected in &policy_result.rejected {
+            println!(
+                "  rejected {} ({}): {:?}",
+                rejected.log_description, rejected.operated_by, rejected.accepted
+            );
+        }
+    }
+
     Ok(())
 }
+
+/// Fetches `log`'s current STH and an inclusion proof for `leaf_hash`
+/// against it, verifying the STH signature and the proof itself - proof
+/// that the log actually appended the entry it promised to via the SCT,
+/// rather than just trusting that promise.
+async fn verify_log_inclusion(
+    client: &CtClient,
+    log: &CTLog,
+    leaf_hash: [u8; 32],
+    sct_timestamp: u64,
+    now: u64,
+) -> Result<(), SctRejection> {
+    let sth = client.get_sth().await.map_err(|error| {
+        println!("{error:?} :: Failed to fetch STH");
+        SctRejection::InclusionUnverified
+    })?;
+    sth.verify_signature(log).map_err(|error| {
+        println!("{error:?} :: Failed to verify STH signature");
+        SctRejection::InclusionUnverified
+    })?;
+
+    let proof = client
+        .get_proof_by_hash(leaf_hash, sth.tree_size)
+        .await
+        .map_err(|error| {
+            let age = now.saturating_sub(sct_timestamp / 1000);
+            if age > log.max_merge_delay as u64 {
+                println!(
+                    "warning: no inclusion proof from {} after {}s (mmd {}s): {:?}",
+                    log.description, age, log.max_merge_delay, error
+                );
+            }
+            SctRejection::InclusionUnverified
+        })?;
+
+    let audit_path = proof.audit_path().map_err(|error| {
+        println!("{error:?} :: Failed to decode inclusion proof audit path");
+        SctRejection::InclusionUnverified
+    })?;
+    let root_hash = sth.root_hash().map_err(|error| {
+        println!("{error:?} :: Failed to decode STH root hash");
+        SctRejection::InclusionUnverified
+    })?;
+
+    merkle::verify_inclusion(leaf_hash, proof.leaf_index, sth.tree_size, &audit_path, root_hash)
+        .map_err(|error| {
+            println!("{error:?} :: Failed to verify inclusion proof");
+            SctRejection::InclusionUnverified
+        })
+}
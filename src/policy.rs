@@ -0,0 +1,143 @@
+//! A CT policy checker mirroring the major browsers' "N SCTs from at least
+//! two independent log operators" requirement, scaled by certificate
+//! lifetime, so the tool can report "CT-compliant" / "not compliant"
+//! rather than leaving the caller to eyeball a list of per-SCT errors.
+
+use std::collections::HashSet;
+
+/// Why an individual SCT didn't count towards compliance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SctRejection {
+    UnknownLog,
+    BadSignature,
+    RetiredLog,
+    /// The log's signature over the SCT checked out, but an inclusion proof
+    /// against a verified STH could not be obtained or did not verify - the
+    /// log promised to include the entry but wasn't shown to have kept that
+    /// promise.
+    InclusionUnverified,
+    /// The certificate's `TBSCertificate` wasn't shaped the way this tool's
+    /// hand-rolled DER splicing expects, so a `PrecertEntry` couldn't be
+    /// reconstructed to check this SCT against.
+    MalformedPrecert,
+}
+
+/// The outcome of checking a single SCT against the trusted log list.
+#[derive(Debug, Clone)]
+pub(crate) struct SctOutcome {
+    pub(crate) log_description: String,
+    pub(crate) operated_by: String,
+    pub(crate) accepted: Result<(), SctRejection>,
+}
+
+/// The result of evaluating a certificate's SCTs against policy.
+#[derive(Debug)]
+pub(crate) struct PolicyResult {
+    pub(crate) compliant: bool,
+    pub(crate) counted: Vec<SctOutcome>,
+    pub(crate) rejected: Vec<SctOutcome>,
+    pub(crate) distinct_operators: usize,
+    pub(crate) required_scts: usize,
+    pub(crate) required_operators: usize,
+}
+
+const REQUIRED_DISTINCT_OPERATORS: usize = 2;
+
+/// The minimum number of embedded SCTs required, scaled by certificate
+/// lifetime the way the major browsers' CT policies do: longer-lived
+/// certificates need more independent logs to have seen them.
+fn required_sct_count(validity_days: i64) -> usize {
+    match validity_days {
+        ..=180 => 2,
+        181..=825 => 3,
+        _ => 5,
+    }
+}
+
+/// Evaluates a certificate's per-SCT outcomes against policy.
+pub(crate) fn evaluate(outcomes: Vec<SctOutcome>, validity_days: i64) -> PolicyResult {
+    let (counted, rejected): (Vec<_>, Vec<_>) =
+        outcomes.into_iter().partition(|o| o.accepted.is_ok());
+
+    let distinct_operators = counted
+        .iter()
+        .map(|o| o.operated_by.as_str())
+        .collect::<HashSet<_>>()
+        .len();
+
+    let required_scts = required_sct_count(validity_days);
+    let compliant =
+        counted.len() >= required_scts && distinct_operators >= REQUIRED_DISTINCT_OPERATORS;
+
+    PolicyResult {
+        compliant,
+        counted,
+        rejected,
+        distinct_operators,
+        required_scts,
+        required_operators: REQUIRED_DISTINCT_OPERATORS,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn accepted(operated_by: &str) -> SctOutcome {
+        SctOutcome {
+            log_description: format!("{operated_by} log"),
+            operated_by: operated_by.to_string(),
+            accepted: Ok(()),
+        }
+    }
+
+    fn rejected(operated_by: &str) -> SctOutcome {
+        SctOutcome {
+            log_description: format!("{operated_by} log"),
+            operated_by: operated_by.to_string(),
+            accepted: Err(SctRejection::BadSignature),
+        }
+    }
+
+    #[test]
+    fn required_sct_count_scales_with_validity() {
+        assert_eq!(required_sct_count(180), 2);
+        assert_eq!(required_sct_count(181), 3);
+        assert_eq!(required_sct_count(825), 3);
+        assert_eq!(required_sct_count(826), 5);
+    }
+
+    #[test]
+    fn compliant_with_enough_scts_from_distinct_operators() {
+        let outcomes = vec![accepted("a"), accepted("b")];
+        let result = evaluate(outcomes, 180);
+        assert!(result.compliant);
+        assert_eq!(result.counted.len(), 2);
+        assert_eq!(result.distinct_operators, 2);
+    }
+
+    #[test]
+    fn not_compliant_with_too_few_distinct_operators() {
+        let outcomes = vec![accepted("a"), accepted("a")];
+        let result = evaluate(outcomes, 180);
+        assert!(!result.compliant);
+        assert_eq!(result.distinct_operators, 1);
+    }
+
+    #[test]
+    fn not_compliant_with_too_few_scts_for_a_long_lived_certificate() {
+        let outcomes = vec![accepted("a"), accepted("b"), accepted("c")];
+        let result = evaluate(outcomes, 826);
+        assert!(!result.compliant);
+        assert_eq!(result.required_scts, 5);
+    }
+
+    #[test]
+    fn rejected_scts_do_not_count_towards_compliance() {
+        let outcomes = vec![accepted("a"), accepted("b"), rejected("c")];
+        let result = evaluate(outcomes, 180);
+        assert!(result.compliant);
+        assert_eq!(result.counted.len(), 2);
+        assert_eq!(result.rejected.len(), 1);
+    }
+}
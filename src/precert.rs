@@ -0,0 +1,254 @@
+//! Reconstructs the RFC 6962 §3.2 `PreCert` that an *embedded* SCT actually
+//! covers. An SCT found inside a certificate's own SCT-list extension was
+//! necessarily issued against the precertificate - the final certificate
+//! didn't exist yet when the log signed it - so verifying it needs the
+//! leaf's `TBSCertificate` with that extension stripped back out, paired
+//! with a hash of the issuer's `SubjectPublicKeyInfo`.
+
+use anyhow::{Result, anyhow, ensure};
+use ring::signature::UnparsedPublicKey;
+use sha2::{Digest, Sha256};
+use x509_parser::extensions::SignedCertificateTimestamp;
+
+use crate::CTLog;
+use crate::ct_client::verification_algorithm_for;
+use crate::der::{encode_tlv, read_tlv, skip_tlvs};
+use crate::merkle::{LogEntryType, timestamped_entry_bytes};
+
+const SEQUENCE: u8 = 0x30;
+const EXPLICIT_VERSION: u8 = 0xa0;
+const EXPLICIT_EXTENSIONS: u8 = 0xa3;
+const OBJECT_IDENTIFIER: u8 = 0x06;
+
+/// DER encoding of the embedded-SCT-list extension OID
+/// (1.3.6.1.4.1.11129.2.4.2).
+const SCT_LIST_OID_DER: &[u8] = &[0x2b, 0x06, 0x01, 0x04, 0x01, 0xd6, 0x79, 0x02, 0x04, 0x02];
+
+/// Builds the RFC 6962 §3.2 `PreCert` - `issuer_key_hash || TBSCertificate`
+/// (with the SCT list extension removed), the latter length-prefixed per
+/// its `opaque<1..2^24-1>` encoding - for the leaf/issuer pair.
+pub(crate) fn build_precert_entry(leaf_der: &[u8], issuer_der: &[u8]) -> Result<Vec<u8>> {
+    let issuer_key_hash = issuer_spki_hash(issuer_der)?;
+    let tbs = strip_sct_list_extension(leaf_der)?;
+
+    let mut entry = Vec::with_capacity(issuer_key_hash.len() + 3 + tbs.len());
+    entry.extend_from_slice(&issuer_key_hash);
+    entry.extend_from_slice(&(tbs.len() as u32).to_be_bytes()[1..]);
+    entry.extend_from_slice(&tbs);
+    Ok(entry)
+}
+
+/// Verifies an embedded SCT's signature over a `PrecertEntry`.
+///
+/// The pinned `sct` crate has no support for `PrecertEntry` - it always
+/// signs/verifies as `X509Entry` and length-prefixes a single `cert` blob
+/// itself - so it cannot be used here. Instead this rebuilds the exact
+/// `digitally-signed` bytes RFC 6962 §3.2 defines for a `PrecertEntry` SCT
+/// and verifies them directly against the log's public key.
+pub(crate) fn verify_precert_sct(
+    log: &CTLog,
+    sct: &SignedCertificateTimestamp,
+    precert_entry: &[u8],
+    now: u64,
+) -> Result<()> {
+    ensure!(
+        sct.timestamp / 1000 <= now,
+        "SCT timestamp is in the future"
+    );
+
+    let signed = timestamped_entry_bytes(
+        sct.timestamp,
+        LogEntryType::PrecertEntry,
+        precert_entry,
+        sct.extensions.0,
+    );
+
+    let algorithm =
+        verification_algorithm_for(sct.signature.hash_alg_id, sct.signature.sign_alg_id)?;
+    UnparsedPublicKey::new(algorithm, &log.key)
+        .verify(&signed, sct.signature.data)
+        .map_err(|_| anyhow!("precert SCT signature verification failed for {}", log.description))
+}
+
+/// SHA-256 over the issuer's `SubjectPublicKeyInfo`, the 7th field of
+/// `TBSCertificate` (after the optional `version`).
+fn issuer_spki_hash(issuer_der: &[u8]) -> Result<[u8; 32]> {
+    let (tag, cert_content, _) = read_tlv(issuer_der)?;
+    ensure!(tag == SEQUENCE, "issuer certificate is not a DER SEQUENCE");
+    let (tbs_tag, tbs_content, _) = read_tlv(cert_content)?;
+    ensure!(
+        tbs_tag == SEQUENCE,
+        "issuer TBSCertificate is not a DER SEQUENCE"
+    );
+
+    let mut rest = tbs_content;
+    if rest.first() == Some(&EXPLICIT_VERSION) {
+        let (_, _, consumed) = read_tlv(rest)?;
+        rest = &rest[consumed..];
+    }
+    // serialNumber, signature, issuer, validity, subject
+    rest = skip_tlvs(rest, 5)?;
+
+    let (spki_tag, _, spki_len) = read_tlv(rest)?;
+    ensure!(
+        spki_tag == SEQUENCE,
+        "issuer subjectPublicKeyInfo is not a DER SEQUENCE"
+    );
+    Ok(Sha256::digest(&rest[..spki_len]).into())
+}
+
+/// Returns a certificate's `TBSCertificate` DER with its embedded SCT list
+/// extension removed, re-encoding every length it touches.
+fn strip_sct_list_extension(cert_der: &[u8]) -> Result<Vec<u8>> {
+    let (tag, cert_content, _) = read_tlv(cert_der)?;
+    ensure!(tag == SEQUENCE, "certificate is not a DER SEQUENCE");
+    let (tbs_tag, tbs_content, _) = read_tlv(cert_content)?;
+    ensure!(tbs_tag == SEQUENCE, "TBSCertificate is not a DER SEQUENCE");
+
+    let mut after_version = tbs_content;
+    if after_version.first() == Some(&EXPLICIT_VERSION) {
+        let (_, _, consumed) = read_tlv(after_version)?;
+        after_version = &after_version[consumed..];
+    }
+    // serialNumber, signature, issuer, validity, subject, subjectPublicKeyInfo
+    let rest = skip_tlvs(after_version, 6)?;
+    let before_extensions = &tbs_content[..tbs_content.len() - rest.len()];
+
+    let (ext_wrapper_tag, ext_wrapper_content, ext_wrapper_len) = read_tlv(rest)?;
+    ensure!(
+        ext_wrapper_tag == EXPLICIT_EXTENSIONS,
+        "TBSCertificate has no extensions to strip the SCT list from"
+    );
+    ensure!(
+        rest.len() == ext_wrapper_len,
+        "unexpected trailing bytes after TBSCertificate extensions"
+    );
+
+    let (ext_seq_tag, ext_seq_content, _) = read_tlv(ext_wrapper_content)?;
+    ensure!(ext_seq_tag == SEQUENCE, "Extensions is not a DER SEQUENCE");
+
+    let mut new_extensions = Vec::with_capacity(ext_seq_content.len());
+    let mut found = false;
+    let mut remaining = ext_seq_content;
+    while !remaining.is_empty() {
+        let (ext_tag, ext_content, consumed) = read_tlv(remaining)?;
+        ensure!(ext_tag == SEQUENCE, "Extension is not a DER SEQUENCE");
+        let (oid_tag, oid_value, _) = read_tlv(ext_content)?;
+        ensure!(
+            oid_tag == OBJECT_IDENTIFIER,
+            "Extension does not start with an OID"
+        );
+        if oid_value == SCT_LIST_OID_DER {
+            found = true;
+        } else {
+            new_extensions.extend_from_slice(&remaining[..consumed]);
+        }
+        remaining = &remaining[consumed..];
+    }
+    ensure!(
+        found,
+        "certificate has no embedded SCT list extension to strip"
+    );
+
+    let new_ext_seq = encode_tlv(SEQUENCE, &new_extensions);
+    let new_ext_wrapper = encode_tlv(EXPLICIT_EXTENSIONS, &new_ext_seq);
+
+    let mut new_tbs_content = Vec::with_capacity(before_extensions.len() + new_ext_wrapper.len());
+    new_tbs_content.extend_from_slice(before_extensions);
+    new_tbs_content.extend_from_slice(&new_ext_wrapper);
+
+    Ok(encode_tlv(SEQUENCE, &new_tbs_content))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn der_integer(n: u8) -> Vec<u8> {
+        encode_tlv(0x02, &[n])
+    }
+
+    fn filler_fields(n: u8) -> Vec<u8> {
+        (1..=n).flat_map(der_integer).collect()
+    }
+
+    fn der_extension(oid: &[u8], value: &[u8]) -> Vec<u8> {
+        let mut content = encode_tlv(OBJECT_IDENTIFIER, oid);
+        content.extend_from_slice(&encode_tlv(0x04, value)); // OCTET STRING
+        encode_tlv(SEQUENCE, &content)
+    }
+
+    /// A minimal DER "certificate" shaped just enough to exercise
+    /// `strip_sct_list_extension`: six filler `TBSCertificate` fields
+    /// standing in for serialNumber..subjectPublicKeyInfo, followed by an
+    /// `[3] EXPLICIT Extensions`.
+    fn make_cert(extensions: &[Vec<u8>]) -> Vec<u8> {
+        let mut tbs_content = filler_fields(6);
+        let ext_seq_content: Vec<u8> = extensions.iter().flatten().copied().collect();
+        let ext_wrapper = encode_tlv(EXPLICIT_EXTENSIONS, &encode_tlv(SEQUENCE, &ext_seq_content));
+        tbs_content.extend_from_slice(&ext_wrapper);
+        encode_tlv(SEQUENCE, &encode_tlv(SEQUENCE, &tbs_content))
+    }
+
+    /// A minimal DER "certificate" shaped just enough to exercise
+    /// `issuer_spki_hash`: five filler fields standing in for
+    /// serialNumber..subject, followed by a `spki` TLV.
+    fn make_issuer(spki: &[u8]) -> Vec<u8> {
+        let mut tbs_content = filler_fields(5);
+        tbs_content.extend_from_slice(spki);
+        encode_tlv(SEQUENCE, &encode_tlv(SEQUENCE, &tbs_content))
+    }
+
+    fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+        haystack.windows(needle.len()).any(|w| w == needle)
+    }
+
+    #[test]
+    fn issuer_spki_hash_hashes_the_whole_spki_tlv() {
+        let spki = encode_tlv(SEQUENCE, b"issuer-public-key");
+        let issuer = make_issuer(&spki);
+
+        let hash = issuer_spki_hash(&issuer).unwrap();
+        assert_eq!(hash.as_slice(), Sha256::digest(&spki).as_slice());
+    }
+
+    #[test]
+    fn strip_sct_list_extension_removes_only_the_sct_extension() {
+        let sct_ext = der_extension(SCT_LIST_OID_DER, b"sct-list-bytes");
+        let other_ext = der_extension(&[0x55, 0x1d, 0x0e], b"other-bytes");
+        let leaf = make_cert(&[sct_ext.clone(), other_ext.clone()]);
+
+        let stripped = strip_sct_list_extension(&leaf).unwrap();
+
+        assert!(!contains(&stripped, &sct_ext));
+        assert!(contains(&stripped, &other_ext));
+    }
+
+    #[test]
+    fn strip_sct_list_extension_fails_without_an_sct_extension() {
+        let other_ext = der_extension(&[0x55, 0x1d, 0x0e], b"other-bytes");
+        let leaf = make_cert(&[other_ext]);
+
+        assert!(strip_sct_list_extension(&leaf).is_err());
+    }
+
+    #[test]
+    fn build_precert_entry_length_prefixes_the_tbs() {
+        let spki = encode_tlv(SEQUENCE, b"issuer-public-key");
+        let issuer = make_issuer(&spki);
+        let leaf = make_cert(&[der_extension(SCT_LIST_OID_DER, b"sct-list-bytes")]);
+
+        let entry = build_precert_entry(&leaf, &issuer).unwrap();
+
+        let issuer_key_hash = issuer_spki_hash(&issuer).unwrap();
+        assert_eq!(&entry[..32], &issuer_key_hash);
+
+        let tbs_len = u32::from_be_bytes([0, entry[32], entry[33], entry[34]]) as usize;
+        let tbs = &entry[35..];
+        assert_eq!(tbs.len(), tbs_len);
+
+        let (tag, _, consumed) = read_tlv(tbs).unwrap();
+        assert_eq!(tag, SEQUENCE);
+        assert_eq!(consumed, tbs.len());
+    }
+}